@@ -0,0 +1,97 @@
+//! The `enki-lsp` binary: speaks the Language Server Protocol over stdio
+//! using `Content-Length`-framed JSON-RPC messages. Document requests and
+//! notifications are handled by [`enki::lsp::Server`]; this binary owns the
+//! transport loop and answers the `shutdown`/`exit` lifecycle messages itself.
+
+use enki::lsp::json::Json;
+use enki::lsp::Server;
+use std::io::{self, BufRead, Write};
+
+fn main() {
+    let stdin = io::stdin();
+    let mut stdin = stdin.lock();
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let mut server = Server::new();
+
+    while let Some(message) = read_message(&mut stdin) {
+        let method = message.get("method").and_then(Json::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Json::Null);
+
+        match message.get("id").cloned() {
+            Some(id) => {
+                // "shutdown" isn't wired into `Server` since it has no
+                // document-backed result to compute - just acknowledge it
+                // and wait for the "exit" notification below.
+                let result = if method == "shutdown" {
+                    Json::Null
+                } else {
+                    server.handle_request(method, &params)
+                };
+                write_message(&mut stdout, &response(id, result));
+            }
+            None => {
+                if method == "exit" {
+                    break;
+                }
+                if let Some((uri, diagnostics)) = server.handle_notification(method, &params) {
+                    write_message(&mut stdout, &publish_diagnostics(&uri, diagnostics));
+                }
+            }
+        }
+    }
+}
+
+/// Reads one `Content-Length`-framed JSON-RPC message, or `None` at EOF.
+fn read_message(input: &mut impl BufRead) -> Option<Json> {
+    let mut content_length = None;
+
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            content_length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0u8; content_length?];
+    input.read_exact(&mut body).ok()?;
+    Json::parse(std::str::from_utf8(&body).ok()?)
+}
+
+fn write_message(output: &mut impl Write, message: &Json) {
+    let body = message.to_json_string();
+    let _ = write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+    let _ = output.flush();
+}
+
+fn response(id: Json, result: Json) -> Json {
+    Json::object(vec![
+        ("jsonrpc", Json::String("2.0".to_string())),
+        ("id", id),
+        ("result", result),
+    ])
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Json) -> Json {
+    Json::object(vec![
+        ("jsonrpc", Json::String("2.0".to_string())),
+        (
+            "method",
+            Json::String("textDocument/publishDiagnostics".to_string()),
+        ),
+        (
+            "params",
+            Json::object(vec![
+                ("uri", Json::String(uri.to_string())),
+                ("diagnostics", diagnostics),
+            ]),
+        ),
+    ])
+}
@@ -0,0 +1,305 @@
+//! A flat, structure-free tokenizer over raw markdown source, modeled on
+//! `rustc_lexer`: the same input always yields the same token stream no
+//! matter the surrounding context, and [`crate::markdown::MarkdownParser`]
+//! is the one place that turns that stream into a tree (headers, emphasis
+//! nesting, code spans). Keeping the two separate means anything that only
+//! wants cheap tokens - a syntax highlighter, say - doesn't have to pay
+//! for the full AST, and the parser no longer has to
+//! re-scan characters it already walked past to figure out run lengths.
+
+/// A byte offset range into the original input, as a half-open `[start, end)`
+/// interval. Kept separate from `std::ops::Range` so it can derive `Copy` and
+/// be embedded directly in tokens without borrow-checker friction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Span {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+}
+
+/// A 0-indexed `line:column` pair, with `column` counting Unicode scalar
+/// values from the start of the line (not bytes, not grapheme clusters).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Position {
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+}
+
+/// One primitive token straight off the character stream, carrying no tree
+/// structure of its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Token {
+    /// A run of one or more `#`, however long - the parser is the one that
+    /// decides whether it's a valid header level or too deep.
+    Hash(usize, Span),
+    /// A run of one to three `*`. A longer run tokenizes as multiple `Star`
+    /// tokens, since three is the most `*`/`` ` `` ever mean together in
+    /// this dialect (`*`, `**`, `***`).
+    Star(usize, Span),
+    /// A run of one to three `` ` ``, same capping rule as [`Token::Star`].
+    Backtick(usize, Span),
+    Newline(Span),
+    /// A maximal run of anything else. Most of a document's bytes end up
+    /// here; the parser is responsible for recognizing finer structure
+    /// inside it, like `:shortcode:` emoji.
+    Text(Span),
+}
+
+impl Token {
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            Token::Hash(_, span)
+            | Token::Star(_, span)
+            | Token::Backtick(_, span)
+            | Token::Newline(span)
+            | Token::Text(span) => *span,
+        }
+    }
+
+    /// Shifts this token's span by `delta` bytes, leaving its payload alone.
+    /// Used to keep tokens after an edited region lined up with the new text
+    /// without re-lexing them.
+    pub(crate) fn shift(self, delta: isize) -> Token {
+        let shift = |span: Span| Span {
+            start: (span.start as isize + delta) as usize,
+            end: (span.end as isize + delta) as usize,
+        };
+
+        match self {
+            Token::Hash(n, span) => Token::Hash(n, shift(span)),
+            Token::Star(n, span) => Token::Star(n, shift(span)),
+            Token::Backtick(n, span) => Token::Backtick(n, shift(span)),
+            Token::Newline(span) => Token::Newline(shift(span)),
+            Token::Text(span) => Token::Text(shift(span)),
+        }
+    }
+}
+
+/// Tokenizes source text and tracks the byte/line bookkeeping needed to
+/// translate offsets into [`Span`]s and [`Position`]s.
+pub(crate) struct Lexer {
+    chars: Vec<char>,
+    chars_len: usize,
+    /// `byte_offsets[i]` is the byte offset of `chars[i]` in the original
+    /// input; `byte_offsets[chars_len]` is the total byte length. Needed
+    /// because `chars` is indexed by char position, which desyncs from byte
+    /// position as soon as the input has any multi-byte character.
+    byte_offsets: Vec<usize>,
+    /// Char indices where each line begins, always starting with `0`.
+    line_starts: Vec<usize>,
+    input: String,
+}
+
+impl Lexer {
+    pub(crate) fn new(input: &str) -> Self {
+        let chars: Vec<char> = input.chars().collect();
+        let chars_len = chars.len();
+
+        let mut byte_offsets = Vec::with_capacity(chars_len + 1);
+        let mut running = 0;
+        for ch in &chars {
+            byte_offsets.push(running);
+            running += ch.len_utf8();
+        }
+        byte_offsets.push(running);
+
+        let mut line_starts = vec![0];
+        for (i, ch) in chars.iter().enumerate() {
+            if *ch == '\n' {
+                line_starts.push(i + 1);
+            }
+        }
+
+        Self {
+            chars_len,
+            chars,
+            byte_offsets,
+            line_starts,
+            input: input.to_string(),
+        }
+    }
+
+    pub(crate) fn text(&self) -> &str {
+        &self.input
+    }
+
+    /// Converts a byte offset into the original input into a `line:column`
+    /// position, with `column` counting Unicode scalar values.
+    pub(crate) fn offset_to_position(&self, byte_offset: usize) -> Position {
+        let char_idx = self
+            .byte_offsets
+            .partition_point(|&b| b <= byte_offset)
+            .saturating_sub(1);
+        let line = self
+            .line_starts
+            .partition_point(|&s| s <= char_idx)
+            .saturating_sub(1);
+        let column = char_idx - self.line_starts[line];
+
+        Position { line, column }
+    }
+
+    /// The inverse of [`Self::offset_to_position`]: converts a 0-indexed
+    /// `line:column` position (column counting Unicode scalar values) back
+    /// into a byte offset into the original input. Out-of-range lines or
+    /// columns are clamped rather than panicking.
+    pub(crate) fn position_to_offset(&self, line: usize, column: usize) -> usize {
+        let line = line.min(self.line_starts.len() - 1);
+        // The end of this line, excluding its `\n`.
+        let line_end = self
+            .line_starts
+            .get(line + 1)
+            .map_or(self.chars_len, |next| next - 1);
+        let char_idx = (self.line_starts[line] + column).min(line_end);
+
+        self.byte_offsets[char_idx]
+    }
+
+    /// Applies a single-line edit to `input`/`chars`/`byte_offsets`/
+    /// `line_starts` in place, touching only the parts at or after the edit -
+    /// unlike [`Self::new`], which always walks the whole input to rebuild
+    /// these from scratch. The caller guarantees neither the replaced text
+    /// nor `new_text` contains a newline, so the number of lines is
+    /// unchanged and `line_starts` only needs its later entries shifted.
+    pub(crate) fn patch(&mut self, edit_range: Span, new_text: &str) {
+        let char_start = self
+            .byte_offsets
+            .binary_search(&edit_range.start)
+            .expect("edit must start on a char boundary");
+        let char_end = self
+            .byte_offsets
+            .binary_search(&edit_range.end)
+            .expect("edit must end on a char boundary");
+
+        let new_chars: Vec<char> = new_text.chars().collect();
+        let delta_chars = new_chars.len() as isize - (char_end - char_start) as isize;
+        let byte_delta =
+            new_text.len() as isize - (edit_range.end - edit_range.start) as isize;
+
+        let mut new_offsets = Vec::with_capacity(new_chars.len());
+        let mut running = edit_range.start;
+        for ch in &new_chars {
+            new_offsets.push(running);
+            running += ch.len_utf8();
+        }
+
+        self.chars.splice(char_start..char_end, new_chars);
+        self.input.replace_range(edit_range.start..edit_range.end, new_text);
+        self.chars_len = self.chars.len();
+
+        let new_chars_end = char_start + new_offsets.len();
+        self.byte_offsets.splice(char_start..char_end, new_offsets);
+        for offset in &mut self.byte_offsets[new_chars_end..] {
+            *offset = (*offset as isize + byte_delta) as usize;
+        }
+
+        let first_shifted = self.line_starts.partition_point(|&s| s <= char_start);
+        for line_start in &mut self.line_starts[first_shifted..] {
+            *line_start = (*line_start as isize + delta_chars) as usize;
+        }
+    }
+
+    /// Builds the [`Span`] covering the char range `[start_char, end_char)`,
+    /// translating both ends to true UTF-8 byte offsets.
+    fn span(&self, start_char: usize, end_char: usize) -> Span {
+        Span {
+            start: self.byte_offsets[start_char],
+            end: self.byte_offsets[end_char],
+        }
+    }
+
+    /// Tokenizes the whole input into a flat stream of primitive tokens.
+    /// Never panics or rejects input: every character ends up inside some
+    /// token, the same way `rustc_lexer::tokenize` always produces a full
+    /// token stream.
+    pub(crate) fn tokenize(&self) -> Vec<Token> {
+        let mut tokens = Vec::with_capacity(self.chars_len / 4 + 1);
+        let mut offset = 0;
+
+        while offset < self.chars_len {
+            let ch = self.chars[offset];
+            let start = offset;
+
+            match ch {
+                '#' => {
+                    while offset < self.chars_len && self.chars[offset] == '#' {
+                        offset += 1;
+                    }
+                    tokens.push(Token::Hash(offset - start, self.span(start, offset)));
+                }
+                '\n' => {
+                    offset += 1;
+                    tokens.push(Token::Newline(self.span(start, offset)));
+                }
+                '*' | '`' => {
+                    let mut count = 0;
+                    while offset < self.chars_len && count < 3 && self.chars[offset] == ch {
+                        offset += 1;
+                        count += 1;
+                    }
+                    let span = self.span(start, offset);
+                    tokens.push(if ch == '*' {
+                        Token::Star(count, span)
+                    } else {
+                        Token::Backtick(count, span)
+                    });
+                }
+                _ => {
+                    while offset < self.chars_len
+                        && !matches!(self.chars[offset], '#' | '*' | '`' | '\n')
+                    {
+                        offset += 1;
+                    }
+                    tokens.push(Token::Text(self.span(start, offset)));
+                }
+            }
+        }
+
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenizes_headers_emphasis_and_code() {
+        let lexer = Lexer::new("# Hi *there* `code`\n");
+
+        assert_eq!(
+            lexer.tokenize(),
+            vec![
+                Token::Hash(1, Span { start: 0, end: 1 }),
+                Token::Text(Span { start: 1, end: 5 }),
+                Token::Star(1, Span { start: 5, end: 6 }),
+                Token::Text(Span { start: 6, end: 11 }),
+                Token::Star(1, Span { start: 11, end: 12 }),
+                Token::Text(Span { start: 12, end: 13 }),
+                Token::Backtick(1, Span { start: 13, end: 14 }),
+                Token::Text(Span { start: 14, end: 18 }),
+                Token::Backtick(1, Span { start: 18, end: 19 }),
+                Token::Newline(Span { start: 19, end: 20 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_runs_longer_than_three_into_multiple_tokens() {
+        let lexer = Lexer::new("****");
+
+        assert_eq!(
+            lexer.tokenize(),
+            vec![
+                Token::Star(3, Span { start: 0, end: 3 }),
+                Token::Star(1, Span { start: 3, end: 4 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_long_hash_runs_in_full_unlike_star_and_backtick() {
+        let lexer = Lexer::new("####### Too deep");
+
+        assert_eq!(lexer.tokenize()[0], Token::Hash(7, Span { start: 0, end: 7 }));
+    }
+}
@@ -0,0 +1,3 @@
+pub(crate) mod lexer;
+pub mod lsp;
+pub mod markdown;
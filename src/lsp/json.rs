@@ -0,0 +1,283 @@
+//! A tiny hand-rolled JSON value, parser, and serializer - just enough to
+//! speak JSON-RPC for the LSP server without pulling in `serde_json`.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object(entries: Vec<(&str, Json)>) -> Json {
+        Json::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Json::Number(n) => Some(*n as usize),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    pub fn serialize(&self, out: &mut String) {
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            Json::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => {
+                out.push_str(&(*n as i64).to_string())
+            }
+            Json::Number(n) => out.push_str(&n.to_string()),
+            Json::String(s) => serialize_string(s, out),
+            Json::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    item.serialize(out);
+                }
+                out.push(']');
+            }
+            Json::Object(entries) => {
+                out.push('{');
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    serialize_string(key, out);
+                    out.push(':');
+                    value.serialize(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+
+    pub fn to_json_string(&self) -> String {
+        let mut out = String::new();
+        self.serialize(&mut out);
+        out
+    }
+
+    pub fn parse(input: &str) -> Option<Json> {
+        let mut parser = Parser {
+            chars: input.chars().peekable(),
+        };
+        parser.parse_value()
+    }
+}
+
+fn serialize_string(s: &str, out: &mut String) {
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some(c) if c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    fn parse_value(&mut self) -> Option<Json> {
+        self.skip_whitespace();
+
+        match self.chars.peek()? {
+            'n' => self.parse_literal("null", Json::Null),
+            't' => self.parse_literal("true", Json::Bool(true)),
+            'f' => self.parse_literal("false", Json::Bool(false)),
+            '"' => self.parse_string().map(Json::String),
+            '[' => self.parse_array(),
+            '{' => self.parse_object(),
+            '-' | '0'..='9' => self.parse_number(),
+            _ => None,
+        }
+    }
+
+    fn parse_literal(&mut self, literal: &str, value: Json) -> Option<Json> {
+        for expected in literal.chars() {
+            if self.chars.next()? != expected {
+                return None;
+            }
+        }
+        Some(value)
+    }
+
+    fn parse_string(&mut self) -> Option<String> {
+        self.chars.next()?; // opening quote
+        let mut result = String::new();
+
+        loop {
+            match self.chars.next()? {
+                '"' => return Some(result),
+                '\\' => match self.chars.next()? {
+                    '"' => result.push('"'),
+                    '\\' => result.push('\\'),
+                    '/' => result.push('/'),
+                    'n' => result.push('\n'),
+                    'r' => result.push('\r'),
+                    't' => result.push('\t'),
+                    'u' => {
+                        let code: String = (0..4).filter_map(|_| self.chars.next()).collect();
+                        let code = u32::from_str_radix(&code, 16).ok()?;
+                        result.push(char::from_u32(code)?);
+                    }
+                    _ => return None,
+                },
+                c => result.push(c),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Option<Json> {
+        let mut text = String::new();
+
+        while matches!(self.chars.peek(), Some(c) if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E'))
+        {
+            text.push(self.chars.next().unwrap());
+        }
+
+        text.parse::<f64>().ok().map(Json::Number)
+    }
+
+    fn parse_array(&mut self) -> Option<Json> {
+        self.chars.next()?; // '['
+        let mut items = Vec::new();
+
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.chars.next();
+            return Some(Json::Array(items));
+        }
+
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+
+            match self.chars.next()? {
+                ',' => continue,
+                ']' => return Some(Json::Array(items)),
+                _ => return None,
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Option<Json> {
+        self.chars.next()?; // '{'
+        let mut entries = Vec::new();
+
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.chars.next();
+            return Some(Json::Object(entries));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            if self.chars.next()? != ':' {
+                return None;
+            }
+
+            entries.push((key, self.parse_value()?));
+            self.skip_whitespace();
+
+            match self.chars.next()? {
+                ',' => continue,
+                '}' => return Some(Json::Object(entries)),
+                _ => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_a_representative_message() {
+        let input = r#"{"id":1,"method":"textDocument/didOpen","params":{"ok":true,"items":[1,2,"three"],"nested":{"x":null}}}"#;
+
+        let parsed = Json::parse(input).unwrap();
+
+        assert_eq!(parsed.get("id").and_then(Json::as_usize), Some(1));
+        assert_eq!(
+            parsed.get("method").and_then(Json::as_str),
+            Some("textDocument/didOpen")
+        );
+        assert_eq!(
+            parsed
+                .get("params")
+                .and_then(|p| p.get("items"))
+                .and_then(Json::as_array)
+                .map(<[Json]>::len),
+            Some(3)
+        );
+        assert_eq!(
+            parsed
+                .get("params")
+                .and_then(|p| p.get("nested"))
+                .and_then(|n| n.get("x")),
+            Some(&Json::Null)
+        );
+
+        // Serializing back and re-parsing should be stable.
+        let re_parsed = Json::parse(&parsed.to_json_string()).unwrap();
+        assert_eq!(parsed, re_parsed);
+    }
+
+    #[test]
+    fn escapes_control_characters_and_quotes() {
+        let value = Json::String("line\nwith \"quotes\"\tand tabs".to_string());
+        let serialized = value.to_json_string();
+
+        assert_eq!(Json::parse(&serialized), Some(value));
+    }
+}
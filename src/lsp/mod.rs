@@ -0,0 +1,546 @@
+//! A minimal Language Server Protocol server for Markdown documents, built
+//! directly on [`crate::markdown`]. This module owns the protocol logic
+//! (request/notification handling, outline/folding/diagnostic translation)
+//! as plain functions over [`json::Json`] values so it can be unit tested
+//! without going through actual stdio; the `enki-lsp` binary (`src/bin/enki-lsp.rs`)
+//! adds the `Content-Length`-framed stdio transport and the
+//! `shutdown`/`exit` handling on top.
+
+pub mod json;
+
+use crate::markdown::{
+    Diagnostic, DiagnosticKind, HeaderLevel, MarkdownParser, MarkdownToken, Span, TextEdit,
+    TextToken,
+};
+use json::Json;
+use std::collections::HashMap;
+
+struct Document {
+    parser: MarkdownParser,
+    tokens: Vec<MarkdownToken>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Holds one open document per URI and dispatches LSP requests/notifications
+/// against them.
+pub struct Server {
+    documents: HashMap<String, Document>,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Server {
+            documents: HashMap::new(),
+        }
+    }
+
+    /// Handles one JSON-RPC request and returns the `result` value to send
+    /// back. Unsupported methods return `null`, matching how most language
+    /// servers respond to a capability a client probes but the server
+    /// doesn't actually advertise.
+    pub fn handle_request(&mut self, method: &str, params: &Json) -> Json {
+        match method {
+            "initialize" => initialize_result(),
+            "textDocument/documentSymbol" => self
+                .document(params)
+                .map(|doc| document_symbols_json(&doc.tokens, &doc.parser))
+                .unwrap_or(Json::Null),
+            "textDocument/foldingRange" => self
+                .document(params)
+                .map(|doc| folding_ranges_json(&doc.tokens, &doc.parser))
+                .unwrap_or(Json::Null),
+            _ => Json::Null,
+        }
+    }
+
+    /// Handles one JSON-RPC notification. Returns the URI and fresh
+    /// diagnostics to publish when the notification changed a document's
+    /// text.
+    pub fn handle_notification(&mut self, method: &str, params: &Json) -> Option<(String, Json)> {
+        match method {
+            "textDocument/didOpen" => self.did_open(params),
+            "textDocument/didChange" => self.did_change(params),
+            "textDocument/didClose" => {
+                self.did_close(params);
+                None
+            }
+            _ => None,
+        }
+    }
+
+    fn document(&self, params: &Json) -> Option<&Document> {
+        let uri = params.get("textDocument")?.get("uri")?.as_str()?;
+        self.documents.get(uri)
+    }
+
+    fn did_open(&mut self, params: &Json) -> Option<(String, Json)> {
+        let text_document = params.get("textDocument")?;
+        let uri = text_document.get("uri")?.as_str()?.to_string();
+        let text = text_document.get("text")?.as_str()?;
+
+        let mut parser = MarkdownParser::new(text);
+        let result = parser.parse();
+        let diagnostics_json = diagnostics_json(&result.diagnostics, &parser);
+
+        self.documents.insert(
+            uri.clone(),
+            Document {
+                parser,
+                tokens: result.tokens,
+                diagnostics: result.diagnostics,
+            },
+        );
+
+        Some((uri, diagnostics_json))
+    }
+
+    fn did_close(&mut self, params: &Json) {
+        if let Some(uri) = params
+            .get("textDocument")
+            .and_then(|td| td.get("uri"))
+            .and_then(Json::as_str)
+        {
+            self.documents.remove(uri);
+        }
+    }
+
+    /// Applies each `TextDocumentContentChangeEvent` via
+    /// [`MarkdownParser::reparse`] so both the token tree and the
+    /// diagnostics are updated incrementally - scoped to the edited block -
+    /// rather than re-tokenizing and re-parsing the full buffer on every
+    /// keystroke.
+    fn did_change(&mut self, params: &Json) -> Option<(String, Json)> {
+        let uri = params.get("textDocument")?.get("uri")?.as_str()?.to_string();
+        let document = self.documents.get_mut(&uri)?;
+
+        for change in params.get("contentChanges")?.as_array()? {
+            let new_text = change.get("text")?.as_str()?;
+
+            match change.get("range") {
+                Some(range) => {
+                    let start = position_to_offset(&document.parser, range.get("start")?)?;
+                    let end = position_to_offset(&document.parser, range.get("end")?)?;
+                    let edit = TextEdit {
+                        range: Span { start, end },
+                        new_text,
+                    };
+
+                    document.parser.reparse(
+                        &mut document.tokens,
+                        &mut document.diagnostics,
+                        edit,
+                    );
+                }
+                None => {
+                    document.parser = MarkdownParser::new(new_text);
+                    let result = document.parser.parse();
+                    document.tokens = result.tokens;
+                    document.diagnostics = result.diagnostics;
+                }
+            }
+        }
+
+        let diagnostics_json = diagnostics_json(&document.diagnostics, &document.parser);
+
+        Some((uri, diagnostics_json))
+    }
+}
+
+fn position_to_offset(parser: &MarkdownParser, position: &Json) -> Option<usize> {
+    let line = position.get("line")?.as_usize()?;
+    let character = position.get("character")?.as_usize()?;
+
+    Some(parser.position_to_offset(line, character))
+}
+
+fn initialize_result() -> Json {
+    Json::object(vec![(
+        "capabilities",
+        Json::object(vec![
+            ("documentSymbolProvider", Json::Bool(true)),
+            ("foldingRangeProvider", Json::Bool(true)),
+            (
+                "textDocumentSync",
+                Json::object(vec![
+                    ("openClose", Json::Bool(true)),
+                    ("change", Json::Number(2.0)), // Incremental.
+                ]),
+            ),
+        ]),
+    )])
+}
+
+/// Builds a nested `textDocument/documentSymbol` outline: each header is
+/// nested under the nearest preceding header with a strictly shallower
+/// level (H2 under the preceding H1, and so on).
+fn document_symbols_json(tokens: &[MarkdownToken], parser: &MarkdownParser) -> Json {
+    let headers: Vec<(u8, String, Span)> = tokens
+        .iter()
+        .filter_map(|token| match token {
+            MarkdownToken::Header(level, children, span) => {
+                Some((header_depth(level), header_text(children), *span))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut index = 0;
+    Json::Array(build_outline(&headers, &mut index, 1, parser))
+}
+
+fn build_outline(
+    headers: &[(u8, String, Span)],
+    index: &mut usize,
+    min_level: u8,
+    parser: &MarkdownParser,
+) -> Vec<Json> {
+    let mut symbols = Vec::new();
+
+    while *index < headers.len() {
+        let (level, name, span) = &headers[*index];
+        if *level < min_level {
+            break;
+        }
+
+        *index += 1;
+        let children = build_outline(headers, index, level + 1, parser);
+
+        symbols.push(Json::object(vec![
+            ("name", Json::String(name.clone())),
+            ("kind", Json::Number(15.0)), // SymbolKind.String, a stand-in for "section".
+            ("range", range_json(*span, parser)),
+            ("selectionRange", range_json(*span, parser)),
+            ("children", Json::Array(children)),
+        ]));
+    }
+
+    symbols
+}
+
+fn header_depth(level: &HeaderLevel) -> u8 {
+    match level {
+        HeaderLevel::One => 1,
+        HeaderLevel::Two => 2,
+        HeaderLevel::Three => 3,
+        HeaderLevel::Four => 4,
+        HeaderLevel::Five => 5,
+        HeaderLevel::Six => 6,
+    }
+}
+
+fn header_text(children: &[TextToken]) -> String {
+    children.iter().map(text_token_plain).collect()
+}
+
+fn text_token_plain(token: &TextToken) -> String {
+    match token {
+        TextToken::Text(text, _) => text.clone(),
+        TextToken::Emoji(emoji, _) => emoji.clone(),
+        TextToken::Italic(children, _)
+        | TextToken::Bold(children, _)
+        | TextToken::BoldItalic(children, _)
+        | TextToken::Code(children, _) => children.iter().map(text_token_plain).collect(),
+    }
+}
+
+/// Folding ranges for header sections (a header through the line before the
+/// next header at the same or a shallower level) and for code spans. A code
+/// span, triple-backtick ones included, never crosses a line break, so a
+/// multi-line fenced code block gets no fold of its own.
+fn folding_ranges_json(tokens: &[MarkdownToken], parser: &MarkdownParser) -> Json {
+    let headers: Vec<(u8, Span)> = tokens
+        .iter()
+        .filter_map(|token| match token {
+            MarkdownToken::Header(level, _, span) => Some((header_depth(level), *span)),
+            _ => None,
+        })
+        .collect();
+
+    let document_end = tokens.last().map(MarkdownToken::span).map(|s| s.end);
+
+    let mut ranges: Vec<Span> = headers
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (level, span))| {
+            let end = headers[i + 1..]
+                .iter()
+                .find(|(other_level, _)| other_level <= level)
+                .map(|(_, other_span)| other_span.start)
+                .or(document_end)?;
+
+            (end > span.end).then_some(Span {
+                start: span.start,
+                end,
+            })
+        })
+        .collect();
+
+    for token in tokens {
+        collect_code_folds(token_children(token), &mut ranges);
+    }
+
+    Json::Array(
+        ranges
+            .into_iter()
+            .map(|span| folding_range_json(span, parser))
+            .collect(),
+    )
+}
+
+fn token_children(token: &MarkdownToken) -> &[TextToken] {
+    match token {
+        MarkdownToken::Header(_, children, _) => children,
+        MarkdownToken::Paragraph(children, _) => children,
+        MarkdownToken::NewLine(_) => &[],
+    }
+}
+
+fn collect_code_folds(children: &[TextToken], ranges: &mut Vec<Span>) {
+    for child in children {
+        match child {
+            TextToken::Code(inner, span) => {
+                ranges.push(*span);
+                collect_code_folds(inner, ranges);
+            }
+            TextToken::Italic(inner, _)
+            | TextToken::Bold(inner, _)
+            | TextToken::BoldItalic(inner, _) => collect_code_folds(inner, ranges),
+            TextToken::Text(_, _) | TextToken::Emoji(_, _) => {}
+        }
+    }
+}
+
+fn diagnostics_json(diagnostics: &[Diagnostic], parser: &MarkdownParser) -> Json {
+    Json::Array(
+        diagnostics
+            .iter()
+            .map(|diagnostic| {
+                Json::object(vec![
+                    ("range", range_json(diagnostic.span, parser)),
+                    ("severity", Json::Number(1.0)), // DiagnosticSeverity.Error.
+                    ("message", Json::String(diagnostic_message(&diagnostic.kind))),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn diagnostic_message(kind: &DiagnosticKind) -> String {
+    match kind {
+        DiagnosticKind::UnterminatedEmphasis => "Unterminated emphasis or code span".to_string(),
+        DiagnosticKind::UnexpectedChar => "Unexpected character".to_string(),
+        DiagnosticKind::HeaderTooDeep => {
+            "Header level deeper than 6 is not supported; clamped to level 6".to_string()
+        }
+    }
+}
+
+/// Converts a byte [`Span`] into an LSP `Range` of `{line, character}`
+/// positions via `parser`. `character` counts Unicode scalar values, a
+/// simplification of the LSP spec's UTF-16 code unit count that only
+/// differs for characters outside the Basic Multilingual Plane.
+fn range_json(span: Span, parser: &MarkdownParser) -> Json {
+    let start = parser.offset_to_position(span.start);
+    let end = parser.offset_to_position(span.end);
+
+    Json::object(vec![
+        ("start", position_json(start)),
+        ("end", position_json(end)),
+    ])
+}
+
+fn position_json(position: crate::markdown::Position) -> Json {
+    Json::object(vec![
+        ("line", Json::Number(position.line as f64)),
+        ("character", Json::Number(position.column as f64)),
+    ])
+}
+
+fn folding_range_json(span: Span, parser: &MarkdownParser) -> Json {
+    let start = parser.offset_to_position(span.start);
+    // LSP folding ranges are end-inclusive by convention (most clients fold
+    // through the last line containing content), so back off from the
+    // half-open `span.end` by one byte before converting.
+    let end = parser.offset_to_position(span.end.saturating_sub(1));
+
+    Json::object(vec![
+        ("startLine", Json::Number(start.line as f64)),
+        ("endLine", Json::Number(end.line as f64)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> (MarkdownParser, Vec<MarkdownToken>) {
+        let mut parser = MarkdownParser::new(text);
+        let result = parser.parse();
+        (parser, result.tokens)
+    }
+
+    #[test]
+    fn document_symbols_nest_by_header_level() {
+        let (parser, tokens) = parse("# One\n\n## Two\n\n### Three\n\n# Four");
+
+        let outline = document_symbols_json(&tokens, &parser);
+        let top_level = outline.as_array().unwrap();
+
+        assert_eq!(top_level.len(), 2);
+        assert_eq!(top_level[0].get("name").and_then(Json::as_str), Some("One"));
+
+        let one_children = top_level[0].get("children").and_then(Json::as_array).unwrap();
+        assert_eq!(one_children.len(), 1);
+        assert_eq!(
+            one_children[0].get("name").and_then(Json::as_str),
+            Some("Two")
+        );
+
+        let two_children = one_children[0]
+            .get("children")
+            .and_then(Json::as_array)
+            .unwrap();
+        assert_eq!(
+            two_children[0].get("name").and_then(Json::as_str),
+            Some("Three")
+        );
+
+        assert_eq!(
+            top_level[1].get("name").and_then(Json::as_str),
+            Some("Four")
+        );
+    }
+
+    #[test]
+    fn folding_ranges_cover_header_sections_and_code_spans() {
+        let (parser, tokens) = parse("# One\nbody\n# Two\n`code`");
+
+        let ranges = folding_ranges_json(&tokens, &parser);
+        let ranges = ranges.as_array().unwrap();
+
+        // One range for the "# One" section (up to just before "# Two"), one
+        // for the "# Two" section (which, being last, runs to the end of the
+        // document and so nests the code span fold inside it), plus one for
+        // the inline code span itself.
+        assert_eq!(ranges.len(), 3);
+
+        let lines = |range: &Json| {
+            (
+                range.get("startLine").and_then(Json::as_usize).unwrap(),
+                range.get("endLine").and_then(Json::as_usize).unwrap(),
+            )
+        };
+
+        assert_eq!(lines(&ranges[0]), (0, 1));
+        assert_eq!(lines(&ranges[1]), (2, 3));
+        assert_eq!(lines(&ranges[2]), (3, 3));
+    }
+
+    #[test]
+    fn diagnostics_are_translated_to_line_column_ranges() {
+        let mut parser = MarkdownParser::new("# Hello *World");
+        let result = parser.parse();
+
+        let json = diagnostics_json(&result.diagnostics, &parser);
+        let diagnostics = json.as_array().unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].get("message").and_then(Json::as_str),
+            Some("Unterminated emphasis or code span")
+        );
+        assert!(diagnostics[0].get("range").is_some());
+    }
+
+    #[test]
+    fn did_open_then_document_symbol_round_trips_through_the_server() {
+        let mut server = Server::new();
+        let params = Json::object(vec![(
+            "textDocument",
+            Json::object(vec![
+                ("uri", Json::String("file:///doc.md".to_string())),
+                ("text", Json::String("# Title".to_string())),
+            ]),
+        )]);
+
+        server.handle_notification("textDocument/didOpen", &params);
+
+        let request_params = Json::object(vec![(
+            "textDocument",
+            Json::object(vec![("uri", Json::String("file:///doc.md".to_string()))]),
+        )]);
+        let symbols = server.handle_request("textDocument/documentSymbol", &request_params);
+        let symbols = symbols.as_array().unwrap();
+
+        assert_eq!(symbols[0].get("name").and_then(Json::as_str), Some("Title"));
+    }
+
+    #[test]
+    fn did_change_uses_incremental_reparse_and_updates_diagnostics() {
+        let mut server = Server::new();
+        let open_params = Json::object(vec![(
+            "textDocument",
+            Json::object(vec![
+                ("uri", Json::String("file:///doc.md".to_string())),
+                ("text", Json::String("# Hello World!".to_string())),
+            ]),
+        )]);
+        server.handle_notification("textDocument/didOpen", &open_params);
+
+        let change_params = Json::object(vec![
+            (
+                "textDocument",
+                Json::object(vec![("uri", Json::String("file:///doc.md".to_string()))]),
+            ),
+            (
+                "contentChanges",
+                Json::Array(vec![Json::object(vec![
+                    (
+                        "range",
+                        Json::object(vec![
+                            (
+                                "start",
+                                Json::object(vec![
+                                    ("line", Json::Number(0.0)),
+                                    ("character", Json::Number(8.0)),
+                                ]),
+                            ),
+                            (
+                                "end",
+                                Json::object(vec![
+                                    ("line", Json::Number(0.0)),
+                                    ("character", Json::Number(13.0)),
+                                ]),
+                            ),
+                        ]),
+                    ),
+                    ("text", Json::String("Rust".to_string())),
+                ])]),
+            ),
+        ]);
+
+        let (uri, diagnostics) = server
+            .handle_notification("textDocument/didChange", &change_params)
+            .unwrap();
+        assert_eq!(uri, "file:///doc.md");
+        assert_eq!(diagnostics.as_array().unwrap().len(), 0);
+
+        let request_params = Json::object(vec![(
+            "textDocument",
+            Json::object(vec![("uri", Json::String("file:///doc.md".to_string()))]),
+        )]);
+        let symbols = server.handle_request("textDocument/documentSymbol", &request_params);
+        assert_eq!(
+            symbols.as_array().unwrap()[0]
+                .get("name")
+                .and_then(Json::as_str),
+            Some("Hello Rust!")
+        );
+    }
+}
@@ -1,5 +1,9 @@
-#[derive(Debug, PartialEq, Eq)]
-enum HeaderLevel {
+use crate::lexer::{Lexer, Token};
+
+pub(crate) use crate::lexer::{Position, Span};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum HeaderLevel {
     One,
     Two,
     Three,
@@ -8,236 +12,829 @@ enum HeaderLevel {
     Six,
 }
 
+/// A recoverable problem found while parsing, carrying the span of the input
+/// it refers to so callers can render a squiggle or a caret.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Diagnostic {
+    pub(crate) span: Span,
+    pub(crate) kind: DiagnosticKind,
+}
+
+impl Diagnostic {
+    /// Shifts this diagnostic's span by `delta` bytes. Used to keep
+    /// diagnostics outside an edited block lined up with the new text
+    /// without re-deriving them.
+    fn shift(self, delta: isize) -> Diagnostic {
+        Diagnostic {
+            span: shift_span(self.span, delta),
+            kind: self.kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DiagnosticKind {
+    /// A `*`/`` ` ``-style run was opened but never closed before the end of
+    /// its line or of the input.
+    UnterminatedEmphasis,
+    /// A character the parser didn't know how to dispatch on; the offending
+    /// character is skipped so the rest of the document still parses.
+    ///
+    /// The lexer now guarantees every character lands in some token, so the
+    /// parser can no longer make zero progress and this variant is never
+    /// constructed. Kept around rather than removed since callers (the LSP's
+    /// diagnostic-to-message mapping) still match on it exhaustively.
+    #[allow(dead_code)]
+    UnexpectedChar,
+    /// More than 6 `#` characters in a row; clamped down to `HeaderLevel::Six`.
+    HeaderTooDeep,
+}
+
+/// The output of [`MarkdownParser::parse`]: a full token stream plus whatever
+/// diagnostics were collected along the way. Unlike the old `panic!`/`todo!`
+/// based parser, this is always produced in full, even for malformed input.
 #[derive(Debug, PartialEq, Eq)]
-enum MarkdownToken {
-    Header(HeaderLevel, Vec<TextToken>),
-    NewLine,
-    Paragraph(Vec<TextToken>),
+pub struct ParseResult {
+    pub(crate) tokens: Vec<MarkdownToken>,
+    pub(crate) diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum MarkdownToken {
+    Header(HeaderLevel, Vec<TextToken>, Span),
+    NewLine(Span),
+    Paragraph(Vec<TextToken>, Span),
+}
+
+impl MarkdownToken {
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            MarkdownToken::Header(_, _, span) => *span,
+            MarkdownToken::NewLine(span) => *span,
+            MarkdownToken::Paragraph(_, span) => *span,
+        }
+    }
+
+    /// Shifts this token's span, and the span of every token nested inside
+    /// it, by `delta` bytes. Used to keep the tokens after an edited block
+    /// consistent after that block changed length.
+    fn shift(self, delta: isize) -> Self {
+        match self {
+            MarkdownToken::Header(level, children, span) => MarkdownToken::Header(
+                level,
+                shift_text_tokens(children, delta),
+                shift_span(span, delta),
+            ),
+            MarkdownToken::NewLine(span) => MarkdownToken::NewLine(shift_span(span, delta)),
+            MarkdownToken::Paragraph(children, span) => {
+                MarkdownToken::Paragraph(shift_text_tokens(children, delta), shift_span(span, delta))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TextToken {
+    Text(String, Span),
+    Italic(Vec<TextToken>, Span),
+    Bold(Vec<TextToken>, Span),
+    BoldItalic(Vec<TextToken>, Span),
+    Code(Vec<TextToken>, Span),
+    /// A resolved GitHub-style `:shortcode:` sequence, e.g. `:rocket:`. Holds
+    /// the looked-up unicode emoji rather than the raw shortcode; unknown
+    /// shortcodes never reach this variant (see [`MarkdownParser::split_emoji`]).
+    Emoji(String, Span),
+}
+
+impl TextToken {
+    fn shift(self, delta: isize) -> Self {
+        match self {
+            TextToken::Text(text, span) => TextToken::Text(text, shift_span(span, delta)),
+            TextToken::Italic(children, span) => {
+                TextToken::Italic(shift_text_tokens(children, delta), shift_span(span, delta))
+            }
+            TextToken::Bold(children, span) => {
+                TextToken::Bold(shift_text_tokens(children, delta), shift_span(span, delta))
+            }
+            TextToken::BoldItalic(children, span) => {
+                TextToken::BoldItalic(shift_text_tokens(children, delta), shift_span(span, delta))
+            }
+            TextToken::Code(children, span) => {
+                TextToken::Code(shift_text_tokens(children, delta), shift_span(span, delta))
+            }
+            TextToken::Emoji(emoji, span) => TextToken::Emoji(emoji, shift_span(span, delta)),
+        }
+    }
+}
+
+fn shift_text_tokens(children: Vec<TextToken>, delta: isize) -> Vec<TextToken> {
+    children.into_iter().map(|child| child.shift(delta)).collect()
+}
+
+fn shift_span(span: Span, delta: isize) -> Span {
+    Span {
+        start: (span.start as isize + delta) as usize,
+        end: (span.end as isize + delta) as usize,
+    }
+}
+
+/// The character set GitHub allows inside a `:shortcode:` name.
+fn is_shortcode_char(ch: char) -> bool {
+    ch.is_ascii_lowercase() || ch.is_ascii_digit() || matches!(ch, '_' | '+' | '-')
 }
 
+/// Checks whether `text[colon_idx..]` (`colon_idx` being the byte offset of a
+/// `:`) opens a syntactically valid shortcode - `[a-z0-9_+-]+` followed
+/// immediately by a closing `:` with no whitespace in between. Returns the
+/// byte offset just past that closing `:` without looking the name up in the
+/// emoji table, so callers can tell "not shaped like a shortcode" apart from
+/// "shaped like one but unknown".
+fn match_shortcode(text: &str, colon_idx: usize) -> Option<usize> {
+    let rest = &text[colon_idx + 1..];
+    let name_len = rest
+        .find(|ch: char| !is_shortcode_char(ch))
+        .unwrap_or(rest.len());
+
+    if name_len == 0 || rest.as_bytes().get(name_len) != Some(&b':') {
+        return None;
+    }
+
+    Some(colon_idx + 1 + name_len + 1)
+}
+
+/// A compile-time shortcode-to-emoji lookup. A real `phf` map would be the
+/// natural fit here, but this crate has no dependencies to pull one in, so a
+/// `match` - which `rustc` compiles down to a similarly flat jump/compare
+/// table - stands in for it.
+fn emoji_for_shortcode(code: &str) -> Option<&'static str> {
+    Some(match code {
+        "smile" => "😄",
+        "simple_smile" => "🙂",
+        "laughing" | "satisfied" => "😆",
+        "joy" => "😂",
+        "wink" => "😉",
+        "heart" => "❤️",
+        "thumbsup" | "+1" => "👍",
+        "thumbsdown" | "-1" => "👎",
+        "rocket" => "🚀",
+        "tada" => "🎉",
+        "fire" => "🔥",
+        "eyes" => "👀",
+        "100" => "💯",
+        "warning" => "⚠️",
+        "white_check_mark" => "✅",
+        "x" => "❌",
+        "bug" => "🐛",
+        "sparkles" => "✨",
+        _ => return None,
+    })
+}
+
+/// A single-range replacement to apply to a previously parsed document, e.g.
+/// as sent by an editor on every keystroke.
+pub struct TextEdit<'a> {
+    pub(crate) range: Span,
+    pub(crate) new_text: &'a str,
+}
+
+/// The outcome of [`MarkdownParser::reparse`]. `old_tokens`/`old_diagnostics`
+/// are patched in place, so there's nothing left to report but whether the
+/// cheap path was actually taken.
 #[derive(Debug, PartialEq, Eq)]
-enum TextToken {
-    Text(String),
-    Italic(Vec<TextToken>),
-    Bold(Vec<TextToken>),
-    BoldItalic(Vec<TextToken>),
-    Code(Vec<TextToken>),
+pub struct ReparseResult {
+    /// Whether the cheap single-block path was taken. `false` means the
+    /// whole document was re-tokenized from scratch.
+    pub(crate) fast_path: bool,
+}
+
+/// One open `*`/`` ` `` run, tracked on [`MarkdownParser`]'s nesting stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Marker {
+    backtick: bool,
+    count: usize,
+}
+
+impl Marker {
+    fn len(&self) -> usize {
+        self.count
+    }
 }
 
-struct MarkdownParser {
+pub(crate) struct MarkdownParser {
+    lexer: Lexer,
+    tokens: Vec<Token>,
+    /// Index into `tokens` of the next token to consume.
+    pos: usize,
+    /// Byte offset just past the last consumed token; `0` before anything's
+    /// been consumed. Spans are built from this rather than re-deriving byte
+    /// offsets from char positions, since every [`Token`] already carries its
+    /// own byte-accurate [`Span`].
     offset: usize,
-    chars: Vec<char>,
-    chars_len: usize,
-    stack: Vec<String>,
+    stack: Vec<Marker>,
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl MarkdownParser {
     pub fn new(input: &str) -> Self {
-        let chars: Vec<char> = input.chars().collect();
+        let lexer = Lexer::new(input);
+        let tokens = lexer.tokenize();
 
         Self {
+            lexer,
+            tokens,
+            pos: 0,
             offset: 0,
-            chars_len: chars.len(),
-            chars,
             stack: Vec::with_capacity(5),
+            diagnostics: Vec::new(),
         }
     }
 
-    pub fn parse(&mut self) -> Vec<MarkdownToken> {
-        let chars_len = self.chars.len();
-        let mut result = Vec::with_capacity(10);
-
-        while self.offset < chars_len {
-            let ch = self.chars[self.offset];
-
-            match ch {
-                '#' => result.push(self.parse_header_or_text()),
-                '\n' => result.push(self.parse_new_line()),
-                _ => panic!("Unexpected token {}.", ch),
-            }
-        }
+    /// Converts a byte offset into the original input into a `line:column`
+    /// position, with `column` counting Unicode scalar values.
+    pub fn offset_to_position(&self, byte_offset: usize) -> Position {
+        self.lexer.offset_to_position(byte_offset)
+    }
 
-        eprintln!("Stack: {:?}", self.stack);
+    /// The inverse of [`Self::offset_to_position`]: converts a 0-indexed
+    /// `line:column` position (column counting Unicode scalar values) back
+    /// into a byte offset into the original input. Out-of-range lines or
+    /// columns are clamped rather than panicking.
+    pub(crate) fn position_to_offset(&self, line: usize, column: usize) -> usize {
+        self.lexer.position_to_offset(line, column)
+    }
 
-        result
+    fn current(&self) -> Option<Token> {
+        self.tokens.get(self.pos).copied()
     }
 
-    fn parse_header_or_text(&mut self) -> MarkdownToken {
-        // Figure out the level of the header (should be between 1 and 6)
-        let mut header_ch_count = 0;
-        while self.offset < self.chars_len && self.chars[self.offset] == '#' {
-            header_ch_count += 1;
-            self.offset += 1;
-        }
+    /// Consumes the current token and returns it, advancing `self.offset` to
+    /// its end.
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.pos];
+        self.pos += 1;
+        self.offset = token.span().end;
+        token
+    }
 
-        if self.offset == self.chars_len {
-            return MarkdownToken::Paragraph(vec![]);
+    /// Builds the [`Span`] covering from `start` up to whatever has been
+    /// consumed so far.
+    fn span_from(&self, start: usize) -> Span {
+        Span {
+            start,
+            end: self.offset,
         }
+    }
 
-        let current_ch = self.chars[self.offset];
+    /// Whether a style run is currently open inside a code span (one or more
+    /// backtick markers on the nesting stack). Emoji shortcodes are left as
+    /// literal text in that context; `*` emphasis inside a code span is still
+    /// parsed.
+    fn in_code(&self) -> bool {
+        self.stack.iter().any(|marker| marker.backtick)
+    }
 
-        if current_ch == ' ' {
-            self.offset += 1;
+    /// Parses the whole input into a token stream. This never panics: anything
+    /// the parser can't make sense of is recorded as a [`Diagnostic`] and the
+    /// parse keeps going, the same way `rustc_lexer` always produces a full
+    /// token stream instead of erroring out.
+    pub fn parse(&mut self) -> ParseResult {
+        let mut result = Vec::with_capacity(10);
 
-            let header_level = match header_ch_count {
-                1 => HeaderLevel::One,
-                2 => HeaderLevel::Two,
-                3 => HeaderLevel::Three,
-                4 => HeaderLevel::Four,
-                5 => HeaderLevel::Five,
-                6 => HeaderLevel::Six,
-                _ => HeaderLevel::Six,
-            };
+        while let Some(token) = self.current() {
+            match token {
+                Token::Hash(_, _) => result.push(self.parse_header_or_text()),
+                Token::Newline(span) => {
+                    self.advance();
+                    result.push(MarkdownToken::NewLine(span));
+                }
+                _ => {
+                    let start = token.span().start;
+                    let children = self.parse_text_tokens();
+                    result.push(MarkdownToken::Paragraph(children, self.span_from(start)));
+                }
+            }
+        }
 
-            self.parse_header(header_level)
-        } else {
-            todo!("Text parsing will be done later")
+        // Every push onto `self.stack` happens inside `get_styled_text_token`,
+        // and every one of its return paths (EOF, a newline, or
+        // `compact_text_token` closing the run) pops the marker it pushed
+        // before returning - including nested/recursive calls - so the
+        // stack is always empty again by the time the loop above exits.
+        // Assert the invariant rather than handling a case that can't occur.
+        debug_assert!(self.stack.is_empty());
+
+        ParseResult {
+            tokens: result,
+            diagnostics: std::mem::take(&mut self.diagnostics),
         }
     }
 
-    fn parse_header(&mut self, header_level: HeaderLevel) -> MarkdownToken {
-        let text_tokens = self.parse_text_tokens();
+    fn parse_header_or_text(&mut self) -> MarkdownToken {
+        let Some(Token::Hash(count, hash_span)) = self.current() else {
+            unreachable!("caller only dispatches here for a Hash token");
+        };
+        let start = hash_span.start;
+
+        let next_is_space = matches!(
+            self.tokens.get(self.pos + 1),
+            Some(Token::Text(span)) if self.lexer.text().as_bytes()[span.start] == b' '
+        );
 
-        MarkdownToken::Header(header_level, text_tokens)
+        if !next_is_space {
+            // Not actually a header (no space after the `#` run) - `#` isn't
+            // special outside of block start, so it folds into the plain
+            // text run like any other character.
+            let children = self.parse_text_tokens();
+            return MarkdownToken::Paragraph(children, self.span_from(start));
+        }
+
+        self.advance();
+
+        let header_level = match count {
+            1 => HeaderLevel::One,
+            2 => HeaderLevel::Two,
+            3 => HeaderLevel::Three,
+            4 => HeaderLevel::Four,
+            5 => HeaderLevel::Five,
+            6 => HeaderLevel::Six,
+            _ => {
+                self.diagnostics.push(Diagnostic {
+                    span: hash_span,
+                    kind: DiagnosticKind::HeaderTooDeep,
+                });
+                HeaderLevel::Six
+            }
+        };
+
+        self.skip_one_leading_space();
+        let children = self.parse_text_tokens();
+        MarkdownToken::Header(header_level, children, self.span_from(start))
     }
 
-    fn parse_text_tokens(&mut self) -> Vec<TextToken> {
-        if self.offset == self.chars_len {
-            return vec![];
+    /// Drops the single space that separates a header's `#` run from its
+    /// content, shrinking the following `Text` token in place rather than
+    /// consuming it whole.
+    fn skip_one_leading_space(&mut self) {
+        let Some(Token::Text(span)) = self.current() else {
+            return;
+        };
+
+        if span.end - span.start == 1 {
+            self.advance();
+        } else if let Token::Text(span) = &mut self.tokens[self.pos] {
+            span.start += 1;
         }
+    }
 
+    fn parse_text_tokens(&mut self) -> Vec<TextToken> {
         let mut tokens = Vec::with_capacity(10);
 
-        while self.offset < self.chars_len {
-            let ch = self.chars[self.offset];
-
-            if ch == '\n' {
+        while let Some(token) = self.current() {
+            if matches!(token, Token::Newline(_)) {
                 break;
             }
 
-            let token = match ch {
-                '*' | '`' => self.get_styled_text_token(),
-                _ => self.parse_text(),
-            };
-
-            tokens.push(token);
+            match token {
+                Token::Star(_, _) | Token::Backtick(_, _) => {
+                    tokens.push(self.get_styled_text_token());
+                }
+                _ => tokens.extend(self.parse_plain_run()),
+            }
         }
 
         tokens
     }
 
-    fn get_styled_text_token(&mut self) -> TextToken {
-        //This special character will be either * or ` characters
-        let spec_ch = self.chars[self.offset];
-        let mut spec_characters = Vec::with_capacity(3);
-        let mut ch_count = 0;
-
-        // Calculate how many special characters we have to identify the style
-        while self.offset < self.chars_len && ch_count < 3 && self.chars[self.offset] == spec_ch {
-            spec_characters.push(spec_ch);
-            self.offset += 1;
-            ch_count += 1;
+    /// Merges consecutive `Text`/`Hash` tokens into one literal run (`#`
+    /// isn't special once we're past block start) and splits out any
+    /// `:shortcode:` emoji it contains.
+    fn parse_plain_run(&mut self) -> Vec<TextToken> {
+        let start = self.current().unwrap().span().start;
+
+        while matches!(self.current(), Some(Token::Text(_) | Token::Hash(_, _))) {
+            self.advance();
+        }
+
+        self.split_emoji(self.span_from(start))
+    }
+
+    /// Splits the literal text covered by `span` into `Text`/`Emoji` pieces
+    /// wherever a valid `:shortcode:` appears, unless [`Self::in_code`] -
+    /// inside a code span the whole run stays literal text.
+    fn split_emoji(&self, span: Span) -> Vec<TextToken> {
+        let text = &self.lexer.text()[span.start..span.end];
+
+        if self.in_code() {
+            return vec![TextToken::Text(text.to_string(), span)];
+        }
+
+        let mut pieces = Vec::new();
+        let mut literal_start = 0;
+        let bytes = text.as_bytes();
+        let mut i = 0;
+
+        while i < bytes.len() {
+            if bytes[i] == b':' {
+                if let Some(end) = match_shortcode(text, i) {
+                    if literal_start < i {
+                        pieces.push(TextToken::Text(
+                            text[literal_start..i].to_string(),
+                            Span {
+                                start: span.start + literal_start,
+                                end: span.start + i,
+                            },
+                        ));
+                    }
+
+                    let code = &text[i + 1..end - 1];
+                    let piece_span = Span {
+                        start: span.start + i,
+                        end: span.start + end,
+                    };
+                    pieces.push(match emoji_for_shortcode(code) {
+                        Some(emoji) => TextToken::Emoji(emoji.to_string(), piece_span),
+                        None => TextToken::Text(text[i..end].to_string(), piece_span),
+                    });
+
+                    i = end;
+                    literal_start = i;
+                    continue;
+                }
+            }
+
+            i += text[i..].chars().next().map_or(1, char::len_utf8);
         }
 
-        if self.offset == self.chars_len {
-            return TextToken::Text("".to_string());
+        if literal_start < text.len() || pieces.is_empty() {
+            pieces.push(TextToken::Text(
+                text[literal_start..].to_string(),
+                Span {
+                    start: span.start + literal_start,
+                    end: span.end,
+                },
+            ));
         }
 
-        let spec_ch_str: String = spec_characters.into_iter().collect();
+        pieces
+    }
+
+    fn get_styled_text_token(&mut self) -> TextToken {
+        let (backtick, count, start_span) = match self.current() {
+            Some(Token::Star(n, span)) => (false, n, span),
+            Some(Token::Backtick(n, span)) => (true, n, span),
+            _ => unreachable!("caller only dispatches here for a Star/Backtick token"),
+        };
+        let start = start_span.start;
 
-        self.stack.push(spec_ch_str);
+        self.advance();
+        self.stack.push(Marker { backtick, count });
 
         let mut tokens = Vec::with_capacity(10);
 
-        while self.offset < self.chars_len {
-            let ch = self.chars[self.offset];
-
-            if ch == spec_ch {
-                if let Some(last) = self.stack.last()
-                    && self.is_styled_text_token_closure()
-                {
-                    self.offset += last.len();
-                    return self.compact_text_token(tokens);
-                } else {
-                    let token = self.get_styled_text_token();
-                    tokens.push(token);
+        loop {
+            let Some(token) = self.current() else {
+                self.stack.pop();
+                return self.unterminated_literal(start);
+            };
+
+            match token {
+                Token::Newline(_) => {
+                    // An emphasis run can't cross a line break in this
+                    // dialect - treat it the same as running out of input,
+                    // rather than looping on it forever.
+                    self.stack.pop();
+                    return self.unterminated_literal(start);
+                }
+                Token::Star(n, _) if !backtick => {
+                    if let Some(end) = self.close_run(n) {
+                        return self.compact_text_token(tokens, Span { start, end });
+                    }
+                    tokens.push(self.get_styled_text_token());
                 }
-            } else {
-                match ch {
-                    '*' | '`' => tokens.push(self.get_styled_text_token()),
-                    _ => tokens.push(self.parse_text()),
+                Token::Backtick(n, _) if backtick => {
+                    if let Some(end) = self.close_run(n) {
+                        return self.compact_text_token(tokens, Span { start, end });
+                    }
+                    tokens.push(self.get_styled_text_token());
                 }
+                Token::Star(_, _) | Token::Backtick(_, _) => {
+                    tokens.push(self.get_styled_text_token());
+                }
+                _ => tokens.extend(self.parse_plain_run()),
             }
         }
+    }
+
+    /// Checks whether a run of `run_len` markers closes the open one on top
+    /// of the stack. A run exactly as long as the open marker always does. A
+    /// longer run only closes it if the run exactly covers the open marker
+    /// plus zero or more enclosing markers of the same character counted
+    /// outward (e.g. `***` closing a `**` nested inside a `*`) - otherwise
+    /// the run isn't shaped like a closer at all, and the caller should
+    /// treat it as a brand new nested marker instead. When it does close,
+    /// only the open marker's own length is consumed here, leaving any
+    /// remainder in place as a smaller token for the enclosing frame to see
+    /// next - which is how one `***` run can close two levels in turn
+    /// without re-scanning any characters to work out the split.
+    fn close_run(&mut self, run_len: usize) -> Option<usize> {
+        if !self.run_closes_from_top(run_len) {
+            return None;
+        }
+
+        let needed = self.stack.last().unwrap().len();
+        let token_start = self.current().unwrap().span().start;
+        let end = token_start + needed;
 
-        if let Some(_) = self.stack.last() {
-            self.compact_text_token(tokens)
+        if run_len == needed {
+            self.advance();
         } else {
-            return TextToken::Text("".to_string());
+            self.shrink_current_token(needed);
         }
-    }
 
-    fn parse_text(&mut self) -> TextToken {
-        let mut text_chs = Vec::with_capacity(100);
+        Some(end)
+    }
 
-        while self.offset < self.chars_len {
-            let ch = self.chars[self.offset];
+    /// Whether a run of `run_len` markers exactly covers the open marker on
+    /// top of the stack plus zero or more enclosing markers of the same
+    /// character, counted outward with no gaps or leftover.
+    fn run_closes_from_top(&self, run_len: usize) -> bool {
+        let backtick = self.stack.last().unwrap().backtick;
+        let mut remaining = run_len;
 
-            if ch == '`' || ch == '*' || ch == '\n' {
+        for marker in self.stack.iter().rev() {
+            if marker.backtick != backtick || marker.count > remaining {
                 break;
             }
-
-            text_chs.push(ch);
-            self.offset += 1;
+            remaining -= marker.count;
+            if remaining == 0 {
+                return true;
+            }
         }
 
-        let text_token_str: String = text_chs.into_iter().collect();
+        false
+    }
 
-        TextToken::Text(text_token_str)
+    /// Drops `consumed` characters from the front of the current (fully
+    /// same-character) `Star`/`Backtick` token in place, leaving the
+    /// remainder for the next call to [`Self::current`] to see.
+    fn shrink_current_token(&mut self, consumed: usize) {
+        match &mut self.tokens[self.pos] {
+            Token::Star(n, span) | Token::Backtick(n, span) => {
+                *n -= consumed;
+                span.start += consumed;
+            }
+            _ => unreachable!("only called when the current token is a Star/Backtick run"),
+        }
     }
 
-    fn compact_text_token(&mut self, tokens: Vec<TextToken>) -> TextToken {
-        let spec_ch_str = self.stack.pop().unwrap();
+    fn compact_text_token(&mut self, tokens: Vec<TextToken>, span: Span) -> TextToken {
+        let marker = self.stack.pop().unwrap();
 
-        match spec_ch_str.as_str() {
-            "*" => TextToken::Italic(tokens),
-            "**" => TextToken::Bold(tokens),
-            "***" => TextToken::BoldItalic(tokens),
-            "`" | "``" | "```" => TextToken::Code(tokens),
-            _ => panic!("Invalid Special Character \"{}\"", spec_ch_str),
+        match (marker.backtick, marker.count) {
+            (false, 1) => TextToken::Italic(tokens, span),
+            (false, 2) => TextToken::Bold(tokens, span),
+            (false, 3) => TextToken::BoldItalic(tokens, span),
+            (true, _) => TextToken::Code(tokens, span),
+            (false, n) => unreachable!("lexer caps `*` runs at 3, got {n}"),
         }
     }
 
-    fn is_styled_text_token_closure(&self) -> bool {
-        let special_ch = self.chars[self.offset];
-        let stack_str: String = self.stack.clone().into_iter().rev().collect();
+    /// Records an [`UnterminatedEmphasis`](DiagnosticKind::UnterminatedEmphasis)
+    /// diagnostic for `start..self.offset` and returns that slice verbatim as
+    /// a literal `Text` token.
+    fn unterminated_literal(&mut self, start: usize) -> TextToken {
+        let span = self.span_from(start);
 
-        let mut offset = self.offset;
-        let mut chs = Vec::with_capacity(3);
+        self.diagnostics.push(Diagnostic {
+            span,
+            kind: DiagnosticKind::UnterminatedEmphasis,
+        });
 
-        while offset < self.chars_len && self.chars[offset] == special_ch {
-            chs.push(self.chars[offset]);
+        TextToken::Text(self.lexer.text()[span.start..span.end].to_string(), span)
+    }
 
-            offset += 1;
+    /// Applies a single-range edit to the document this parser was built
+    /// from. When `edit` lands entirely inside one top-level block and can't
+    /// have changed its block type, only that block's text is re-lexed, and
+    /// `old_tokens`/`old_diagnostics` are patched in place - the block itself
+    /// is replaced and everything after it is shifted, but the (typically
+    /// much larger) prefix before it is left completely untouched rather
+    /// than cloned. The flat token stream backing this parser is patched the
+    /// same way. Otherwise the whole document is reparsed from scratch.
+    pub fn reparse(
+        &mut self,
+        old_tokens: &mut Vec<MarkdownToken>,
+        old_diagnostics: &mut Vec<Diagnostic>,
+        edit: TextEdit,
+    ) -> ReparseResult {
+        if let Some(patch) = self.try_reparse_single_block(old_tokens, &edit) {
+            for token in &mut self.tokens[patch.flat_end..] {
+                *token = token.shift(patch.delta);
+            }
+            self.tokens
+                .splice(patch.flat_start..patch.flat_end, patch.flat_replacement);
+            self.lexer.patch(edit.range, edit.new_text);
+
+            self.pos = 0;
+            self.offset = 0;
+            self.stack.clear();
+            self.diagnostics.clear();
+
+            let tail: Vec<MarkdownToken> = old_tokens
+                .split_off(patch.block_index + 1)
+                .into_iter()
+                .map(|token| token.shift(patch.delta))
+                .collect();
+            old_tokens.pop();
+            old_tokens.push(patch.new_block);
+            old_tokens.extend(tail);
+
+            let mut diagnostics = Vec::with_capacity(old_diagnostics.len());
+            for diagnostic in old_diagnostics.drain(..) {
+                if diagnostic.span.end <= patch.block_span.start {
+                    diagnostics.push(diagnostic);
+                } else if diagnostic.span.start >= patch.block_span.end {
+                    diagnostics.push(diagnostic.shift(patch.delta));
+                } // else: scoped to the replaced block, superseded below.
+            }
+            diagnostics.extend(patch.diagnostics);
+            *old_diagnostics = diagnostics;
+
+            return ReparseResult { fast_path: true };
         }
 
-        let read_chs: String = chs.into_iter().collect();
+        let new_input = self.apply_edit(&edit);
+        *self = MarkdownParser::new(&new_input);
 
-        self.stack.last().unwrap() == &read_chs || read_chs == stack_str
+        let result = self.parse();
+        *old_tokens = result.tokens;
+        *old_diagnostics = result.diagnostics;
+        ReparseResult { fast_path: false }
     }
 
-    fn parse_new_line(&mut self) -> MarkdownToken {
-        self.offset += 1;
+    fn apply_edit(&self, edit: &TextEdit) -> String {
+        let input = self.lexer.text();
+        let mut new_input = String::with_capacity(
+            input.len() - (edit.range.end - edit.range.start) + edit.new_text.len(),
+        );
+        new_input.push_str(&input[..edit.range.start]);
+        new_input.push_str(edit.new_text);
+        new_input.push_str(&input[edit.range.end..]);
+        new_input
+    }
+
+    fn try_reparse_single_block(
+        &self,
+        old_tokens: &[MarkdownToken],
+        edit: &TextEdit,
+    ) -> Option<SingleBlockPatch> {
+        // `old_tokens` is sorted by span since blocks are parsed in document
+        // order, so the containing block (if any) can be found by binary
+        // search rather than scanning every earlier block. A zero-length
+        // edit sitting exactly on a block boundary is ambiguous between the
+        // block ending there and the one starting there - among candidates,
+        // prefer the earlier block, matching what a front-to-back scan would
+        // have found first.
+        let prefix_len = old_tokens.partition_point(|token| token.span().start <= edit.range.start);
+        let block_index = old_tokens[..prefix_len].partition_point(|token| token.span().end < edit.range.end);
+        if block_index == prefix_len {
+            return None;
+        }
+        let block_span = old_tokens[block_index].span();
+
+        let input = self.lexer.text();
+        let removed = &input[edit.range.start..edit.range.end];
+        if removed.contains('\n') || edit.new_text.contains('\n') {
+            return None; // could merge or split blocks
+        }
+        if edit.range.start == block_span.start && edit.new_text.starts_with('#') {
+            return None; // could turn this block into a header
+        }
+
+        let delta = edit.new_text.len() as isize - removed.len() as isize;
+
+        let mut block_text =
+            String::with_capacity(block_span.end - block_span.start + edit.new_text.len());
+        block_text.push_str(&input[block_span.start..edit.range.start]);
+        block_text.push_str(edit.new_text);
+        block_text.push_str(&input[edit.range.end..block_span.end]);
+
+        // Re-lexing/re-parsing is scoped to just this block's (typically
+        // tiny) text, not the whole document - that's the whole point of the
+        // fast path. The block parser's flat tokens and diagnostics are
+        // reused below instead of re-lexing/re-diagnosing the block again.
+        let mut block_parser = MarkdownParser::new(&block_text);
+        let block_result = block_parser.parse();
+        let mut block_tokens = block_result.tokens;
+        if block_tokens.len() != 1 {
+            return None;
+        }
+
+        let new_block = block_tokens.pop().unwrap();
+        if std::mem::discriminant(&new_block) != std::mem::discriminant(&old_tokens[block_index]) {
+            return None;
+        }
 
-        MarkdownToken::NewLine
+        let new_block = new_block.shift(block_span.start as isize);
+
+        let flat_start = self
+            .tokens
+            .partition_point(|token| token.span().start < block_span.start);
+        let flat_end = self
+            .tokens
+            .partition_point(|token| token.span().end <= block_span.end);
+        let flat_replacement = block_parser
+            .tokens
+            .into_iter()
+            .map(|token| token.shift(block_span.start as isize))
+            .collect();
+        let diagnostics = block_result
+            .diagnostics
+            .into_iter()
+            .map(|diagnostic| diagnostic.shift(block_span.start as isize))
+            .collect();
+
+        Some(SingleBlockPatch {
+            block_index,
+            block_span,
+            new_block,
+            delta,
+            flat_start,
+            flat_end,
+            flat_replacement,
+            diagnostics,
+        })
     }
 }
 
+/// What [`MarkdownParser::try_reparse_single_block`] found: enough to splice
+/// the replaced block into `old_tokens` in place (leaving the untouched
+/// prefix before it uncloned) and to keep the flat lexer-token stream and
+/// diagnostics in sync without re-lexing the document.
+struct SingleBlockPatch {
+    /// Index into `old_tokens` of the block being replaced.
+    block_index: usize,
+    /// The old, pre-edit span of the block that was replaced; used to
+    /// partition `old_diagnostics` into "keep as-is", "shift", and
+    /// "superseded by `diagnostics`".
+    block_span: Span,
+    /// Already shifted to its absolute position in the new document.
+    new_block: MarkdownToken,
+    /// `edit.new_text.len() as isize - (edit.range.end - edit.range.start) as isize`.
+    delta: isize,
+    /// `self.tokens[flat_start..flat_end]` is the old block's flat tokens.
+    flat_start: usize,
+    flat_end: usize,
+    /// Already shifted to absolute positions in the new document.
+    flat_replacement: Vec<Token>,
+    /// Diagnostics from re-parsing the block, already shifted to absolute
+    /// positions in the new document.
+    diagnostics: Vec<Diagnostic>,
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
 
+    const DUMMY: Span = Span { start: 0, end: 0 };
+
+    fn erase_text_spans(tokens: Vec<TextToken>) -> Vec<TextToken> {
+        tokens.into_iter().map(erase_text_span).collect()
+    }
+
+    fn erase_text_span(token: TextToken) -> TextToken {
+        match token {
+            TextToken::Text(text, _) => TextToken::Text(text, DUMMY),
+            TextToken::Italic(children, _) => TextToken::Italic(erase_text_spans(children), DUMMY),
+            TextToken::Bold(children, _) => TextToken::Bold(erase_text_spans(children), DUMMY),
+            TextToken::BoldItalic(children, _) => {
+                TextToken::BoldItalic(erase_text_spans(children), DUMMY)
+            }
+            TextToken::Code(children, _) => TextToken::Code(erase_text_spans(children), DUMMY),
+            TextToken::Emoji(emoji, _) => TextToken::Emoji(emoji, DUMMY),
+        }
+    }
+
+    /// Test-only helper: real parses always carry real spans, but most of
+    /// these tests only care about the token tree's shape, so spans are
+    /// normalized away here and checked precisely in the dedicated span
+    /// tests below instead.
+    fn erase_spans(tokens: Vec<MarkdownToken>) -> Vec<MarkdownToken> {
+        tokens
+            .into_iter()
+            .map(|token| match token {
+                MarkdownToken::Header(level, children, _) => {
+                    MarkdownToken::Header(level, erase_text_spans(children), DUMMY)
+                }
+                MarkdownToken::NewLine(_) => MarkdownToken::NewLine(DUMMY),
+                MarkdownToken::Paragraph(children, _) => {
+                    MarkdownToken::Paragraph(erase_text_spans(children), DUMMY)
+                }
+            })
+            .collect()
+    }
+
     #[test]
     fn parse_header_1() {
         let input = "# Hello World!";
@@ -246,10 +843,11 @@ mod tests {
         let result = markdown_parser.parse();
 
         assert_eq!(
-            result,
+            erase_spans(result.tokens),
             vec![MarkdownToken::Header(
                 HeaderLevel::One,
-                vec![TextToken::Text("Hello World!".to_string())]
+                vec![TextToken::Text("Hello World!".to_string(), DUMMY)],
+                DUMMY
             )]
         );
     }
@@ -262,14 +860,15 @@ mod tests {
         let result = markdown_parser.parse();
 
         assert_eq!(
-            result,
+            erase_spans(result.tokens),
             vec![MarkdownToken::Header(
                 HeaderLevel::One,
                 vec![
-                    TextToken::Text("Hello ".to_string()),
-                    TextToken::Italic(vec![TextToken::Text("World".to_string())]),
-                    TextToken::Text("!".to_string())
-                ]
+                    TextToken::Text("Hello ".to_string(), DUMMY),
+                    TextToken::Italic(vec![TextToken::Text("World".to_string(), DUMMY)], DUMMY),
+                    TextToken::Text("!".to_string(), DUMMY)
+                ],
+                DUMMY
             )]
         );
     }
@@ -282,17 +881,21 @@ mod tests {
         let result = markdown_parser.parse();
 
         assert_eq!(
-            result,
+            erase_spans(result.tokens),
             vec![MarkdownToken::Header(
                 HeaderLevel::One,
                 vec![
-                    TextToken::Text("Hello ".to_string()),
-                    TextToken::Italic(vec![
-                        TextToken::Text("World ".to_string()),
-                        TextToken::Bold(vec![TextToken::Text("123".to_string())])
-                    ]),
-                    TextToken::Text("!".to_string())
-                ]
+                    TextToken::Text("Hello ".to_string(), DUMMY),
+                    TextToken::Italic(
+                        vec![
+                            TextToken::Text("World ".to_string(), DUMMY),
+                            TextToken::Bold(vec![TextToken::Text("123".to_string(), DUMMY)], DUMMY)
+                        ],
+                        DUMMY
+                    ),
+                    TextToken::Text("!".to_string(), DUMMY)
+                ],
+                DUMMY
             )]
         );
     }
@@ -305,19 +908,29 @@ mod tests {
         let result = markdown_parser.parse();
 
         assert_eq!(
-            result,
+            erase_spans(result.tokens),
             vec![MarkdownToken::Header(
                 HeaderLevel::One,
                 vec![
-                    TextToken::Text("Hello ".to_string()),
-                    TextToken::Italic(vec![
-                        TextToken::Text("World ".to_string()),
-                        TextToken::Bold(vec![TextToken::Text("123".to_string())]),
-                        TextToken::Text(" ".to_string()),
-                        TextToken::Code(vec![TextToken::Text("i am a code".to_string())])
-                    ]),
-                    TextToken::Text("!".to_string())
-                ]
+                    TextToken::Text("Hello ".to_string(), DUMMY),
+                    TextToken::Italic(
+                        vec![
+                            TextToken::Text("World ".to_string(), DUMMY),
+                            TextToken::Bold(
+                                vec![TextToken::Text("123".to_string(), DUMMY)],
+                                DUMMY
+                            ),
+                            TextToken::Text(" ".to_string(), DUMMY),
+                            TextToken::Code(
+                                vec![TextToken::Text("i am a code".to_string(), DUMMY)],
+                                DUMMY
+                            )
+                        ],
+                        DUMMY
+                    ),
+                    TextToken::Text("!".to_string(), DUMMY)
+                ],
+                DUMMY
             )]
         );
     }
@@ -330,16 +943,23 @@ mod tests {
         let result = markdown_parser.parse();
 
         assert_eq!(
-            result,
+            erase_spans(result.tokens),
             vec![MarkdownToken::Header(
                 HeaderLevel::One,
                 vec![
-                    TextToken::Bold(vec![TextToken::Text("Introduction to".to_string())]),
-                    TextToken::Text(" ".to_string()),
-                    TextToken::BoldItalic(vec![TextToken::Text("Programming".to_string())]),
-                    TextToken::Text(" with ".to_string()),
-                    TextToken::Code(vec![TextToken::Text("Rust".to_string())]),
-                ]
+                    TextToken::Bold(
+                        vec![TextToken::Text("Introduction to".to_string(), DUMMY)],
+                        DUMMY
+                    ),
+                    TextToken::Text(" ".to_string(), DUMMY),
+                    TextToken::BoldItalic(
+                        vec![TextToken::Text("Programming".to_string(), DUMMY)],
+                        DUMMY
+                    ),
+                    TextToken::Text(" with ".to_string(), DUMMY),
+                    TextToken::Code(vec![TextToken::Text("Rust".to_string(), DUMMY)], DUMMY),
+                ],
+                DUMMY
             )]
         );
     }
@@ -352,21 +972,35 @@ mod tests {
         let result = markdown_parser.parse();
 
         assert_eq!(
-            result,
+            erase_spans(result.tokens),
             vec![MarkdownToken::Header(
                 HeaderLevel::One,
                 vec![
-                    TextToken::Bold(vec![TextToken::Text("Introduction to".to_string())]),
-                    TextToken::Text(" ".to_string()),
-                    TextToken::BoldItalic(vec![TextToken::Text("Programming".to_string())]),
-                    TextToken::Text(" with ".to_string()),
-                    TextToken::Code(vec![
-                        TextToken::Text("Rust ".to_string()),
-                        TextToken::Italic(vec![TextToken::Text(
-                            "Programming Language".to_string()
-                        )])
-                    ]),
-                ]
+                    TextToken::Bold(
+                        vec![TextToken::Text("Introduction to".to_string(), DUMMY)],
+                        DUMMY
+                    ),
+                    TextToken::Text(" ".to_string(), DUMMY),
+                    TextToken::BoldItalic(
+                        vec![TextToken::Text("Programming".to_string(), DUMMY)],
+                        DUMMY
+                    ),
+                    TextToken::Text(" with ".to_string(), DUMMY),
+                    TextToken::Code(
+                        vec![
+                            TextToken::Text("Rust ".to_string(), DUMMY),
+                            TextToken::Italic(
+                                vec![TextToken::Text(
+                                    "Programming Language".to_string(),
+                                    DUMMY
+                                )],
+                                DUMMY
+                            )
+                        ],
+                        DUMMY
+                    ),
+                ],
+                DUMMY
             )]
         );
     }
@@ -379,14 +1013,15 @@ mod tests {
         let result = markdown_parser.parse();
 
         assert_eq!(
-            result,
+            erase_spans(result.tokens),
             vec![
                 MarkdownToken::Header(
                     HeaderLevel::One,
-                    vec![TextToken::Text("Hello World!".to_string())]
+                    vec![TextToken::Text("Hello World!".to_string(), DUMMY)],
+                    DUMMY
                 ),
-                MarkdownToken::NewLine,
-                MarkdownToken::NewLine
+                MarkdownToken::NewLine(DUMMY),
+                MarkdownToken::NewLine(DUMMY)
             ]
         );
     }
@@ -399,12 +1034,419 @@ mod tests {
         let result = markdown_parser.parse();
 
         assert_eq!(
-            result,
+            erase_spans(result.tokens),
             vec![MarkdownToken::Header(
                 HeaderLevel::Two,
-                vec![TextToken::Bold(vec![TextToken::Text(
-                    "Hello World!".to_string()
-                )])]
+                vec![TextToken::Bold(
+                    vec![TextToken::Text("Hello World!".to_string(), DUMMY)],
+                    DUMMY
+                )],
+                DUMMY
+            )]
+        );
+    }
+
+    #[test]
+    fn parse_unterminated_emphasis_degrades_to_literal_text() {
+        let input = "# Hello *World";
+
+        let mut markdown_parser = MarkdownParser::new(input);
+        let result = markdown_parser.parse();
+
+        assert_eq!(
+            erase_spans(result.tokens),
+            vec![MarkdownToken::Header(
+                HeaderLevel::One,
+                vec![
+                    TextToken::Text("Hello ".to_string(), DUMMY),
+                    TextToken::Text("*World".to_string(), DUMMY)
+                ],
+                DUMMY
+            )]
+        );
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(
+            result.diagnostics[0].kind,
+            DiagnosticKind::UnterminatedEmphasis
+        );
+    }
+
+    #[test]
+    fn parse_unterminated_emphasis_spanning_a_newline_degrades_to_literal_text() {
+        let input = "*foo\nbar";
+
+        let mut markdown_parser = MarkdownParser::new(input);
+        let result = markdown_parser.parse();
+
+        assert_eq!(
+            erase_spans(result.tokens),
+            vec![
+                MarkdownToken::Paragraph(
+                    vec![TextToken::Text("*foo".to_string(), DUMMY)],
+                    DUMMY
+                ),
+                MarkdownToken::NewLine(DUMMY),
+                MarkdownToken::Paragraph(vec![TextToken::Text("bar".to_string(), DUMMY)], DUMMY),
+            ]
+        );
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(
+            result.diagnostics[0].kind,
+            DiagnosticKind::UnterminatedEmphasis
+        );
+    }
+
+    #[test]
+    fn parse_plain_paragraph_no_longer_panics() {
+        let input = "Just a paragraph, no header here.";
+
+        let mut markdown_parser = MarkdownParser::new(input);
+        let result = markdown_parser.parse();
+
+        assert_eq!(
+            erase_spans(result.tokens),
+            vec![MarkdownToken::Paragraph(
+                vec![TextToken::Text(
+                    "Just a paragraph, no header here.".to_string(),
+                    DUMMY
+                )],
+                DUMMY
+            )]
+        );
+        assert!(result.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn parse_hash_without_space_is_a_paragraph() {
+        let input = "#nope";
+
+        let mut markdown_parser = MarkdownParser::new(input);
+        let result = markdown_parser.parse();
+
+        assert_eq!(
+            erase_spans(result.tokens),
+            vec![MarkdownToken::Paragraph(
+                vec![TextToken::Text("#nope".to_string(), DUMMY)],
+                DUMMY
+            )]
+        );
+        assert!(result.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn parse_header_too_deep_is_clamped_and_diagnosed() {
+        let input = "####### Too deep";
+
+        let mut markdown_parser = MarkdownParser::new(input);
+        let result = markdown_parser.parse();
+
+        assert_eq!(
+            erase_spans(result.tokens),
+            vec![MarkdownToken::Header(
+                HeaderLevel::Six,
+                vec![TextToken::Text("Too deep".to_string(), DUMMY)],
+                DUMMY
+            )]
+        );
+        assert_eq!(result.diagnostics.len(), 1);
+        assert_eq!(result.diagnostics[0].kind, DiagnosticKind::HeaderTooDeep);
+    }
+
+    #[test]
+    fn header_span_covers_full_token_in_bytes() {
+        let input = "# Hello World!";
+
+        let mut markdown_parser = MarkdownParser::new(input);
+        let result = markdown_parser.parse();
+
+        let MarkdownToken::Header(_, children, span) = &result.tokens[0] else {
+            panic!("expected a header token");
+        };
+
+        assert_eq!(*span, Span { start: 0, end: input.len() });
+
+        let TextToken::Text(_, text_span) = &children[0] else {
+            panic!("expected a text token");
+        };
+        assert_eq!(*text_span, Span { start: 2, end: 14 });
+    }
+
+    #[test]
+    fn spans_use_byte_offsets_not_char_offsets_for_multibyte_input() {
+        // "é" is 1 char but 2 bytes, so a naive char-indexed span would read
+        // one byte short of the truth for everything after it.
+        let input = "# é World";
+
+        let mut markdown_parser = MarkdownParser::new(input);
+        let result = markdown_parser.parse();
+
+        let MarkdownToken::Header(_, children, _) = &result.tokens[0] else {
+            panic!("expected a header token");
+        };
+
+        let TextToken::Text(text, span) = &children[0] else {
+            panic!("expected a text token");
+        };
+
+        assert_eq!(text, "é World");
+        assert_eq!(*span, Span { start: 2, end: input.len() });
+    }
+
+    #[test]
+    fn offset_to_position_counts_unicode_scalars_not_bytes() {
+        let input = "# é World\n## Two";
+        let markdown_parser = MarkdownParser::new(input);
+
+        // "é" starts at byte 2; "World" starts at byte 5 (2 + "é".len_utf8()
+        // + 1 space), which is char index 4 on line 0.
+        assert_eq!(
+            markdown_parser.offset_to_position(5),
+            Position { line: 0, column: 4 }
+        );
+
+        let second_line_start = input.find("##").unwrap();
+        assert_eq!(
+            markdown_parser.offset_to_position(second_line_start),
+            Position { line: 1, column: 0 }
+        );
+    }
+
+    #[test]
+    fn reparse_single_block_edit_takes_fast_path() {
+        let input = "# Hello World!\n\nAnother paragraph.";
+        let mut parser = MarkdownParser::new(input);
+        let mut tokens = parser.parse().tokens;
+        let mut diagnostics = Vec::new();
+
+        let start = input.find("World").unwrap();
+        let edit = TextEdit {
+            range: Span {
+                start,
+                end: start + "World".len(),
+            },
+            new_text: "Rust",
+        };
+
+        let result = parser.reparse(&mut tokens, &mut diagnostics, edit);
+        assert!(result.fast_path);
+
+        let new_input = "# Hello Rust!\n\nAnother paragraph.";
+        let expected = MarkdownParser::new(new_input).parse().tokens;
+
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn reparse_edit_introducing_newline_falls_back_to_full_reparse() {
+        let input = "# Hello World!";
+        let mut parser = MarkdownParser::new(input);
+        let mut tokens = parser.parse().tokens;
+        let mut diagnostics = Vec::new();
+
+        let start = input.find("World").unwrap();
+        let edit = TextEdit {
+            range: Span { start, end: start },
+            new_text: "\n# New",
+        };
+
+        let result = parser.reparse(&mut tokens, &mut diagnostics, edit);
+        assert!(!result.fast_path);
+
+        let new_input = "# Hello \n# NewWorld!";
+        let expected = MarkdownParser::new(new_input).parse().tokens;
+
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn reparse_edit_spanning_two_blocks_falls_back_to_full_reparse() {
+        let input = "# Hello\n\nWorld";
+        let mut parser = MarkdownParser::new(input);
+        let mut tokens = parser.parse().tokens;
+        let mut diagnostics = Vec::new();
+
+        let start = input.find("Hello").unwrap();
+        let end = input.find("World").unwrap();
+        let edit = TextEdit {
+            range: Span { start, end },
+            new_text: "Hi\n\n",
+        };
+
+        let result = parser.reparse(&mut tokens, &mut diagnostics, edit);
+        assert!(!result.fast_path);
+
+        let new_input = "# Hi\n\nWorld";
+        let expected = MarkdownParser::new(new_input).parse().tokens;
+
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn reparse_edit_inserting_hash_at_block_start_falls_back_to_full_reparse() {
+        let input = "Just a paragraph.";
+        let mut parser = MarkdownParser::new(input);
+        let mut tokens = parser.parse().tokens;
+        let mut diagnostics = Vec::new();
+
+        let edit = TextEdit {
+            range: Span { start: 0, end: 0 },
+            new_text: "# ",
+        };
+
+        let result = parser.reparse(&mut tokens, &mut diagnostics, edit);
+        assert!(!result.fast_path);
+
+        let new_input = "# Just a paragraph.";
+        let expected = MarkdownParser::new(new_input).parse().tokens;
+
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn reparse_edit_at_exact_block_boundary_takes_fast_path() {
+        let input = "# Hello\n\nWorld";
+        let mut parser = MarkdownParser::new(input);
+        let mut tokens = parser.parse().tokens;
+        let mut diagnostics = Vec::new();
+
+        // Insert right at the end of the header block, not touching the
+        // newline that follows it.
+        let end = input.find('\n').unwrap();
+        let edit = TextEdit {
+            range: Span { start: end, end },
+            new_text: "!",
+        };
+
+        let result = parser.reparse(&mut tokens, &mut diagnostics, edit);
+        assert!(result.fast_path);
+
+        let new_input = "# Hello!\n\nWorld";
+        let expected = MarkdownParser::new(new_input).parse().tokens;
+
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn position_to_offset_is_the_inverse_of_offset_to_position() {
+        let input = "# é World\n## Two";
+        let markdown_parser = MarkdownParser::new(input);
+
+        for byte_offset in [0, 2, 5, 10, 11, input.len()] {
+            let position = markdown_parser.offset_to_position(byte_offset);
+            assert_eq!(
+                markdown_parser.position_to_offset(position.line, position.column),
+                byte_offset
+            );
+        }
+    }
+
+    #[test]
+    fn position_to_offset_clamps_out_of_range_input() {
+        let input = "# Hello";
+        let markdown_parser = MarkdownParser::new(input);
+
+        assert_eq!(markdown_parser.position_to_offset(50, 0), 0);
+        assert_eq!(markdown_parser.position_to_offset(0, 50), input.len());
+    }
+
+    #[test]
+    fn parse_emoji_shortcode() {
+        let input = "Nice :rocket: launch";
+
+        let mut markdown_parser = MarkdownParser::new(input);
+        let result = markdown_parser.parse();
+
+        assert_eq!(
+            erase_spans(result.tokens),
+            vec![MarkdownToken::Paragraph(
+                vec![
+                    TextToken::Text("Nice ".to_string(), DUMMY),
+                    TextToken::Emoji("🚀".to_string(), DUMMY),
+                    TextToken::Text(" launch".to_string(), DUMMY),
+                ],
+                DUMMY
+            )]
+        );
+        assert!(result.diagnostics.is_empty());
+    }
+
+    #[test]
+    fn parse_unknown_shortcode_is_literal_text() {
+        let input = ":not_a_real_emoji:";
+
+        let mut markdown_parser = MarkdownParser::new(input);
+        let result = markdown_parser.parse();
+
+        assert_eq!(
+            erase_spans(result.tokens),
+            vec![MarkdownToken::Paragraph(
+                vec![TextToken::Text(":not_a_real_emoji:".to_string(), DUMMY)],
+                DUMMY
+            )]
+        );
+    }
+
+    #[test]
+    fn parse_colon_without_shortcode_is_literal_text() {
+        let input = "Note: this is fine";
+
+        let mut markdown_parser = MarkdownParser::new(input);
+        let result = markdown_parser.parse();
+
+        assert_eq!(
+            erase_spans(result.tokens),
+            vec![MarkdownToken::Paragraph(
+                vec![TextToken::Text("Note: this is fine".to_string(), DUMMY)],
+                DUMMY
+            )]
+        );
+    }
+
+    #[test]
+    fn parse_emoji_nests_inside_bold_and_italic() {
+        let input = "*great :fire: work* **:tada:**";
+
+        let mut markdown_parser = MarkdownParser::new(input);
+        let result = markdown_parser.parse();
+
+        assert_eq!(
+            erase_spans(result.tokens),
+            vec![MarkdownToken::Paragraph(
+                vec![
+                    TextToken::Italic(
+                        vec![
+                            TextToken::Text("great ".to_string(), DUMMY),
+                            TextToken::Emoji("🔥".to_string(), DUMMY),
+                            TextToken::Text(" work".to_string(), DUMMY),
+                        ],
+                        DUMMY
+                    ),
+                    TextToken::Text(" ".to_string(), DUMMY),
+                    TextToken::Bold(
+                        vec![TextToken::Emoji("🎉".to_string(), DUMMY)],
+                        DUMMY
+                    ),
+                ],
+                DUMMY
+            )]
+        );
+    }
+
+    #[test]
+    fn parse_emoji_is_suppressed_inside_code_spans() {
+        let input = "`:rocket:`";
+
+        let mut markdown_parser = MarkdownParser::new(input);
+        let result = markdown_parser.parse();
+
+        assert_eq!(
+            erase_spans(result.tokens),
+            vec![MarkdownToken::Paragraph(
+                vec![TextToken::Code(
+                    vec![TextToken::Text(":rocket:".to_string(), DUMMY)],
+                    DUMMY
+                )],
+                DUMMY
             )]
         );
     }